@@ -1,5 +1,7 @@
-use std::collections::HashMap;
-use crate::transactions::{Transaction, TransactionType};
+use std::collections::{BTreeMap, HashMap};
+use serde::Serialize;
+use crate::error::LedgerError;
+use crate::transactions::{Amount, Transaction, TransactionType};
 
 type ClientID = u16;
 
@@ -24,27 +26,56 @@ impl AccountsCache {
         self.store.get_mut(client_id)
     }
 
-    pub fn initialize_account(&mut self, tx: &Transaction) -> &mut AccountsCache {
+    pub fn initialize_account(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         if let TransactionType::Deposit = tx.transaction_type {
-            let new_account = Account::new(tx.client_id, tx.amount.unwrap());
+            let amount = tx.amount.ok_or(LedgerError::MissingAmount)?;
+            let new_account = Account::new(tx.client_id, amount);
             self.store.insert(tx.client_id, new_account);
+            Ok(())
         } else {
-            // TODO: Return Error.
+            Err(LedgerError::UnknownAccount(tx.client_id))
         }
-        return self
+    }
+
+    // rows returns every account as an AccountRow, sorted by client id, for callers that need
+    // structured output (e.g. writing through a csv::Writer) rather than the pre-formatted
+    // Display string.
+    pub fn rows(&self) -> Vec<AccountRow> {
+        let sorted: BTreeMap<&ClientID, &Account> = self.store.iter().collect();
+        sorted
+            .into_iter()
+            .map(|(client_id, account)| AccountRow {
+                client: *client_id,
+                available: account.available.to_string(),
+                held: account.held.to_string(),
+                total: account.total.to_string(),
+                locked: account.locked,
+            })
+            .collect()
     }
 }
 
+// AccountRow is the serializable, sorted view of an Account used both by AccountsCache::Display
+// and by csv::Writer when the account table is written back out over a socket or stdout.
+#[derive(Serialize)]
+pub struct AccountRow {
+    pub client: ClientID,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+}
+
 // Account represents an individual clients account with the bank.
 pub struct Account {
     // client represents the unique client id for the account in question.
     pub client: u16,
     // available represents the total funds that are available for trading, staking, withdrawal, etc.
-    pub available: f32,
+    pub available: Amount,
     // held represents the total funds that are held for dispute.
-    pub held: f32,
+    pub held: Amount,
     // total represents the total funds. It is a sum of the available funds and the held funds.
-    pub total: f32,
+    pub total: Amount,
     // locked represents whether the account is currently locked. An account becomes locked if a charge back occurs.
     pub locked: bool,
 }
@@ -53,58 +84,55 @@ impl Account {
     // New creates a new Account based on the initial deposit. Because this is a brand new account
     // the funds are available, and no disputes have occurred yet, so the account is not locked and there are
     // no held funds.
-    pub fn new(client_id: u16, initial_deposit: f32) -> Account {
+    pub fn new(client_id: u16, initial_deposit: Amount) -> Account {
         Account {
             client: client_id,
             total: initial_deposit,
-            held: 0.0,
+            held: Amount::zero(),
             available: initial_deposit,
             locked: false,
         }
     }
 
-    pub fn apply_deposit(&mut self, deposit_amt: f32) -> &mut Account {
-        self.total += deposit_amt;
-        self.available += deposit_amt;
-        self
+    pub fn apply_deposit(&mut self, deposit_amt: Amount) -> Result<(), LedgerError> {
+        self.total = self.total.checked_add(deposit_amt).ok_or(LedgerError::Overflow)?;
+        self.available = self.available.checked_add(deposit_amt).ok_or(LedgerError::Overflow)?;
+        Ok(())
     }
 
-    pub fn apply_withdrawal(&mut self, withdrawal_amt: f32) -> &mut Account {
-        if self.available < withdrawal_amt {
-            return self;
-        }
-        self.total -= withdrawal_amt;
-        self.available -= withdrawal_amt;
-        self
+    pub fn apply_withdrawal(&mut self, withdrawal_amt: Amount) -> Result<(), LedgerError> {
+        self.total = self.total.checked_sub(withdrawal_amt).ok_or(LedgerError::Overflow)?;
+        self.available = self.available.checked_sub(withdrawal_amt).ok_or(LedgerError::Overflow)?;
+        Ok(())
     }
 
-    pub fn apply_dispute(&mut self, disputed_amt: f32) -> &mut Account {
-        self.available -= disputed_amt;
-        self.held += disputed_amt;
-        self
+    pub fn apply_dispute(&mut self, disputed_amt: Amount) -> Result<(), LedgerError> {
+        self.available = self.available.checked_sub(disputed_amt).ok_or(LedgerError::Overflow)?;
+        self.held = self.held.checked_add(disputed_amt).ok_or(LedgerError::Overflow)?;
+        Ok(())
     }
 
-    pub fn apply_resolve(&mut self, resolve_amt: f32) -> &mut Account {
-        self.held -= resolve_amt;
-        self.available += resolve_amt;
-        self
+    pub fn apply_resolve(&mut self, resolve_amt: Amount) -> Result<(), LedgerError> {
+        self.held = self.held.checked_sub(resolve_amt).ok_or(LedgerError::Overflow)?;
+        self.available = self.available.checked_add(resolve_amt).ok_or(LedgerError::Overflow)?;
+        Ok(())
     }
 
-    pub fn apply_chargeback(&mut self, chargeback_amt: f32) -> &mut Account {
+    pub fn apply_chargeback(&mut self, chargeback_amt: Amount) -> Result<(), LedgerError> {
+        self.held = self.held.checked_sub(chargeback_amt).ok_or(LedgerError::Overflow)?;
+        self.total = self.total.checked_sub(chargeback_amt).ok_or(LedgerError::Overflow)?;
         self.locked = true;
-        self.held -= chargeback_amt;
-        self.total -= chargeback_amt;
-        self
+        Ok(())
     }
 }
 
 impl std::fmt::Display for AccountsCache {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut out = "client,available,held,total,locked\n".to_string();
-        for (client_id, account) in &self.store {
-            let line = format!("{},{:.4},{:.4},{:.4},{}\n", client_id, account.available, account.held, account.total, account.locked);
+        for row in self.rows() {
+            let line = format!("{},{},{},{},{}\n", row.client, row.available, row.held, row.total, row.locked);
             out.push_str(&line);
         }
         write!(f, "{}", out)
     }
-}
\ No newline at end of file
+}
@@ -2,9 +2,23 @@ use std::process;
 extern crate csv_transaction_processing;
 
 fn main() {
-    let filename = std::env::args().nth(1).expect("no filename provided");
-    if let Err(e) = csv_transaction_processing::run(filename) {
+    let args: Vec<String> = std::env::args().collect();
+
+    let result = match args.get(1).map(String::as_str) {
+        Some("--stdin") => csv_transaction_processing::run_stdin(),
+        Some("--serve") => {
+            let addr = args.get(2).expect("no address provided for --serve");
+            csv_transaction_processing::serve(addr)
+        },
+        Some(filename) => csv_transaction_processing::run(filename.to_string()),
+        None => {
+            eprintln!("usage: csv_transaction_processing <file> | --stdin | --serve <addr>");
+            process::exit(1);
+        },
+    };
+
+    if let Err(e) = result {
         eprintln!("Application error: {}", e);
         process::exit(1);
     };
-}
\ No newline at end of file
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+// LedgerError represents the ways in which applying a transaction to the ledger can fail.
+// Most variants describe a partner-side data error (e.g. a dispute referencing a transaction
+// that doesn't exist) rather than a bug in this program, so callers are expected to log and
+// continue processing the rest of the stream rather than treat every LedgerError as fatal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerError {
+    // NotEnoughFunds is returned when a withdrawal would take an account's available funds negative.
+    NotEnoughFunds,
+    // MissingAmount is returned when a deposit or withdrawal record has no amount field.
+    MissingAmount,
+    // UnknownTx is returned when a dispute, resolve, or chargeback references a transaction id
+    // that was never successfully applied as a deposit or withdrawal. The fields are the client id and tx id.
+    UnknownTx(u16, u32),
+    // UnknownAccount is returned when the first transaction seen for a client isn't a deposit,
+    // so there's no account to initialize. The field is the client id.
+    UnknownAccount(u16),
+    // AlreadyDisputed is returned when a dispute references a transaction that has already been
+    // disputed, whether it is still under dispute or was since resolved or charged back.
+    AlreadyDisputed,
+    // NotDisputed is returned when a resolve or chargeback references a transaction that isn't under dispute.
+    NotDisputed,
+    // FrozenAccount is returned when a transaction targets an account that has been locked by a chargeback.
+    FrozenAccount,
+    // WrongClient is returned when a dispute, resolve, or chargeback references a transaction
+    // that belongs to a different client than the one making the reference.
+    WrongClient,
+    // Overflow is returned when applying a transaction would overflow an account's available,
+    // held, or total balance.
+    Overflow,
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LedgerError::NotEnoughFunds => write!(f, "not enough available funds to complete withdrawal"),
+            LedgerError::MissingAmount => write!(f, "deposit or withdrawal is missing its amount field"),
+            LedgerError::UnknownTx(client_id, tx_id) => {
+                write!(f, "transaction {} referenced by client {} does not exist", tx_id, client_id)
+            },
+            LedgerError::UnknownAccount(client_id) => {
+                write!(f, "client {} has no account and the first transaction seen for them isn't a deposit", client_id)
+            },
+            LedgerError::AlreadyDisputed => write!(f, "transaction has already been disputed"),
+            LedgerError::NotDisputed => write!(f, "transaction is not currently under dispute"),
+            LedgerError::FrozenAccount => write!(f, "account is frozen and cannot accept new transactions"),
+            LedgerError::WrongClient => write!(f, "transaction does not belong to the referencing client"),
+            LedgerError::Overflow => write!(f, "applying transaction would overflow an account balance"),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
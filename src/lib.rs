@@ -22,46 +22,110 @@
 //! $ cargo run -- transactions.csv > accounts.csv
 //! ```
 //!
+//! It can also ingest transactions from stdin, or act as a long-running service that reads a
+//! transaction stream from a TCP connection and writes the account table back to that same
+//! connection -- see [`run_stdin`] and [`serve`].
+//!
 //! ## Tests
 //!
 //! Unit tests have been added in the `src/services.rs` file.
 //!
-//! ## TODOS:
-//! Unfortunately I did not have time to get to appropriate error handling. Ideally I would have built
-//! a base error enum and returned specific errors. I've instead left TODOs for now in places where errors
-//! should have been returned.
+//! ## Errors
+//! Errors applying an individual transaction (e.g. a dispute referencing an unknown tx, or a
+//! withdrawal with no amount) are represented by [`error::LedgerError`]. `process_csv` logs these
+//! and keeps processing the rest of the stream, since they describe a bad partner record rather
+//! than a reason to abort the whole run. Only a malformed CSV row aborts the run entirely.
 //!
-//! Another thing that is less than ideal is that I needed to use an Option type so serde could handle
-//! missing amounts for dispute, resolve, and chargeback transaction types. In my program I assume that for
-//! withdrawal and deposit they exist, and simply unwrap. I should ideally handle the case explicitly
-//! where a withdrawal or deposit line come in with no amount supplied.
+//! Disputes, resolves, and chargebacks carry no `amount` field in the CSV, which is why
+//! `Transaction::amount` is an `Option`.
 
 
 use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use csv::ReaderBuilder;
 use crate::services::TransactionService;
 use crate::transactions::Transaction;
 
 mod accounts;
+mod error;
 mod services;
 mod transactions;
 
-pub fn process_csv(filename: &str) -> Result<String, Box<dyn Error>>{
-    let mut rdr = csv::Reader::from_path(filename)?;
+// configured_reader_builder returns the csv::ReaderBuilder used everywhere this crate reads
+// transactions, so the real pipeline and the tests agree on how rows are parsed. Partner CSV
+// streams pad fields with whitespace (`deposit, 1, 1, 1.0`) and omit the trailing `amount`
+// column entirely on dispute/resolve/chargeback rows, so trimming and flexible row lengths are
+// both required rather than relying on csv's defaults.
+pub(crate) fn configured_reader_builder() -> ReaderBuilder {
+    let mut builder = ReaderBuilder::new();
+    builder.trim(csv::Trim::All).flexible(true);
+    builder
+}
+
+// process_reader runs the core transaction loop over any source of CSV bytes -- a file, stdin,
+// or a socket -- and returns the populated service. The engine already applies one record at a
+// time, so a whole file isn't required; callers decide how to read the bytes and how to surface
+// the resulting account table.
+pub(crate) fn process_reader<R: Read>(reader: R) -> Result<TransactionService, Box<dyn Error>> {
+    let mut rdr = configured_reader_builder().from_reader(reader);
     let mut service = TransactionService::new();
 
     for result in rdr.deserialize() {
         let transaction: Transaction = result?;
-        // Apply transaction to accounts.
-        service.apply_transaction(&transaction);
+        // Apply transaction to accounts. A LedgerError means the partner sent a bad record
+        // (e.g. a dispute referencing an unknown tx) -- log it and keep processing the stream.
+        if let Err(e) = service.apply_transaction(&transaction) {
+            eprintln!("skipping transaction {}: {}", transaction.tx_id, e);
+        }
     }
 
-    let results = service.print_accounts();
+    Ok(service)
+}
 
-    Ok(results)
+pub fn process_csv(filename: &str) -> Result<String, Box<dyn Error>>{
+    let file = std::fs::File::open(filename)?;
+    let service = process_reader(file)?;
+    Ok(service.print_accounts())
 }
 
 pub fn run(filename: String) -> Result<(), Box<dyn Error>> {
     let results = process_csv(&filename)?;
     println!("{}", results);
     Ok(())
+}
+
+// run_stdin reads a transaction stream from stdin until EOF and writes the resulting account
+// table to stdout, e.g. `cat transactions.csv | csv_transaction_processing --stdin`.
+pub fn run_stdin() -> Result<(), Box<dyn Error>> {
+    let service = process_reader(io::stdin())?;
+    write_accounts(io::stdout(), &service)
+}
+
+// serve runs the processor as a long-running service: it accepts TCP connections on addr one at
+// a time, reads each connection's transaction stream to completion, and writes the resulting
+// account table back to that same connection before moving on to the next one.
+pub fn serve(addr: &str) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        if let Err(e) = handle_connection(stream?) {
+            eprintln!("connection error: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    let writer = stream.try_clone()?;
+    let service = process_reader(stream)?;
+    write_accounts(writer, &service)
+}
+
+fn write_accounts<W: Write>(writer: W, service: &TransactionService) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for row in service.account_rows() {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
 }
\ No newline at end of file
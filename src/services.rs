@@ -1,5 +1,6 @@
-use crate::accounts::AccountsCache;
-use crate::transactions::{TransactionCache, TransactionType, Transaction};
+use crate::accounts::{AccountRow, AccountsCache};
+use crate::error::LedgerError;
+use crate::transactions::{TransactionCache, TransactionType, Transaction, TxState};
 
 pub struct TransactionService {
     accounts: AccountsCache,
@@ -15,146 +16,144 @@ impl TransactionService {
     }
 
 
-    pub fn apply_transaction(&mut self, tx: &Transaction) -> &mut TransactionService {
-        self.transactions.insert(tx.clone());
-
+    pub fn apply_transaction(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         // If no client account exists yet, let's create it with an initial deposit.
         if !self.accounts.contains_key(&tx.client_id) {
-            self.accounts.initialize_account(tx);
-            return self;
+            self.accounts.initialize_account(tx)?;
+            self.transactions.insert(tx.clone());
+            return Ok(());
         }
 
         match tx.transaction_type {
-            TransactionType::Deposit => {
-                self.handle_deposit(tx);
-            },
-            TransactionType::Withdrawal => {
-                self.handle_withdrawal(tx);
-            },
-            TransactionType::Dispute => {
-                self.handle_dispute(tx);
-            },
-            TransactionType::Resolve => {
-                self.handle_resolve(tx);
-            },
-            TransactionType::Chargeback => {
-                self.handle_chargeback(tx);
-            },
-        }
-
-        self
+            TransactionType::Deposit => self.handle_deposit(tx),
+            TransactionType::Withdrawal => self.handle_withdrawal(tx),
+            TransactionType::Dispute => self.handle_dispute(tx),
+            TransactionType::Resolve => self.handle_resolve(tx),
+            TransactionType::Chargeback => self.handle_chargeback(tx),
+        }
     }
 
     pub fn print_accounts(&self) -> String {
         self.accounts.to_string()
     }
 
-    fn handle_deposit(&mut self, tx: &Transaction) -> &mut TransactionService {
+    pub fn account_rows(&self) -> Vec<AccountRow> {
+        self.accounts.rows()
+    }
+
+    fn handle_deposit(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         let account = self.accounts.get_mut(&tx.client_id).unwrap();
 
         // If the clients account is locked we should bail.
         if account.locked {
-            // TODO: Return error here.
-            return self;
+            return Err(LedgerError::FrozenAccount);
         }
 
-        account.apply_deposit(tx.amount.unwrap());
+        let amount = tx.amount.ok_or(LedgerError::MissingAmount)?;
+        account.apply_deposit(amount)?;
+
+        // Only a deposit that actually applied is disputable, so it's only inserted into the
+        // cache now rather than before the account update could fail.
+        self.transactions.insert(tx.clone());
 
-        self
+        Ok(())
     }
 
-    fn handle_withdrawal(&mut self, tx: &Transaction) -> &mut TransactionService {
+    fn handle_withdrawal(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         let account = self.accounts.get_mut(&tx.client_id).unwrap();
 
         // If the clients account is locked we should bail.
         if account.locked {
-            // TODO: Return error here.
-            return self;
+            return Err(LedgerError::FrozenAccount);
+        }
+
+        let amount = tx.amount.ok_or(LedgerError::MissingAmount)?;
+        if account.available < amount {
+            return Err(LedgerError::NotEnoughFunds);
         }
 
-        account.apply_withdrawal(tx.amount.unwrap());
+        account.apply_withdrawal(amount)?;
 
-        self
+        // Only a withdrawal that actually applied is disputable, so it's only inserted into the
+        // cache now rather than before the account update could fail.
+        self.transactions.insert(tx.clone());
+
+        Ok(())
     }
 
-    fn handle_dispute(&mut self, tx: &Transaction) -> &mut TransactionService {
+    fn handle_dispute(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         let account = self.accounts.get_mut(&tx.client_id).unwrap();
 
         // If the clients account is locked we should bail.
         if account.locked {
-            // TODO: Return error here.
-            return self;
+            return Err(LedgerError::FrozenAccount);
         }
 
-        // Find transaction in question. If it doesn't exist, assume partner side error.
-        if !self.transactions.contains_key(&tx.tx_id) {
-            // TODO: Throw partner-side error.
-            return self;
+        // Find transaction in question. If it doesn't exist, or belongs to another client,
+        // assume partner side error.
+        let disputed_tx = self.transactions.get_owned_mut(&tx.tx_id, tx.client_id)?;
+
+        if disputed_tx.state != TxState::Processed {
+            return Err(LedgerError::AlreadyDisputed);
         }
 
-        let disputed_tx = self.transactions.get_mut(&tx.tx_id).unwrap();
-        disputed_tx.disputed = true;
+        let amount = disputed_tx.amount.ok_or(LedgerError::MissingAmount)?;
+        disputed_tx.state = TxState::Disputed;
 
-        account.apply_dispute(disputed_tx.amount.unwrap());
+        account.apply_dispute(amount)?;
 
-        self
+        Ok(())
     }
 
-    fn handle_resolve(&mut self, tx: &Transaction) -> &mut TransactionService {
+    fn handle_resolve(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         let account = self.accounts.get_mut(&tx.client_id).unwrap();
 
         // If the clients account is locked we should bail.
         if account.locked {
-            // TODO: Return error here.
-            return self;
+            return Err(LedgerError::FrozenAccount);
         }
 
-        // Find transaction in question. If it doesn't exist, assume partner side error.
-        if !self.transactions.contains_key(&tx.tx_id) {
-            // TODO: Throw partner-side error.
-            return self;
-        }
+        // Find transaction in question. If it doesn't exist, or belongs to another client,
+        // assume partner side error.
+        let resolved_tx = self.transactions.get_owned_mut(&tx.tx_id, tx.client_id)?;
 
-        let resolved_tx = self.transactions.get_mut(&tx.tx_id).unwrap();
-        if !resolved_tx.disputed {
-            // TODO: Return error.
-            return self;
+        if resolved_tx.state != TxState::Disputed {
+            return Err(LedgerError::NotDisputed);
         }
 
+        let amount = resolved_tx.amount.ok_or(LedgerError::MissingAmount)?;
+
         // Using a mutable reference so no need to re-insert.
-        account.apply_resolve(resolved_tx.amount.unwrap());
+        account.apply_resolve(amount)?;
 
-        resolved_tx.disputed = false;
+        resolved_tx.state = TxState::Resolved;
 
-        self
+        Ok(())
     }
 
-    fn handle_chargeback(&mut self, tx: &Transaction) -> &mut TransactionService {
+    fn handle_chargeback(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
         let account = self.accounts.get_mut(&tx.client_id).unwrap();
 
         // If the clients account is locked we should bail.
         if account.locked {
-            // TODO: Return error here.
-            return self;
+            return Err(LedgerError::FrozenAccount);
         }
 
-        // Find transaction in question. If it doesn't exist, assume partner side error.
-        if !self.transactions.contains_key(&tx.tx_id) {
-            // TODO: Throw partner-side error.
-            return self;
-        }
+        // Find transaction in question. If it doesn't exist, or belongs to another client,
+        // assume partner side error.
+        let chargeback_tx = self.transactions.get_owned_mut(&tx.tx_id, tx.client_id)?;
 
-        let chargeback_tx = self.transactions.get_mut(&tx.tx_id).unwrap();
-        if !chargeback_tx.disputed {
-            // TODO: Return error.
-            return self;
+        if chargeback_tx.state != TxState::Disputed {
+            return Err(LedgerError::NotDisputed);
         }
 
-        account.apply_chargeback(chargeback_tx.amount.unwrap());
+        let amount = chargeback_tx.amount.ok_or(LedgerError::MissingAmount)?;
 
-        chargeback_tx.disputed = false;
+        account.apply_chargeback(amount)?;
 
-        self
+        chargeback_tx.state = TxState::ChargedBack;
+
+        Ok(())
     }
 }
 
@@ -163,22 +162,19 @@ impl TransactionService {
 mod tests {
     use super::*;
 
-    // This test is flaky because order isn't guaranteed from a hashmap, which means the print results may be out of order.
-    // This fits the requirements but makes testing print output harder. One solution would be to either not test print output
-    // at all, or use something like IndexMap which guarantees insertion order is respected.
-    //
-    // Another option could be only passing a portion of the sample data to reflect a single client.
+    // Rows are sorted by client id in AccountsCache::Display, so this asserts the full
+    // multi-client output in a fixed order rather than just checking membership.
     #[test]
     fn sample_data_passes() {
-        let sample_data = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,2,2,2.0\ndeposit,1,3,2.0\nwithdrawal,1,4,1.5\nwithdrawal,2,5,3.0";
-        let mut rdr = csv::Reader::from_reader(sample_data.as_bytes());
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,2,2,2.0\ndeposit,1,3,2.0\nwithdrawal,1,4,1.5\nwithdrawal,2,5,1.0";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
         let mut service = TransactionService::new();
         for result in rdr.deserialize() {
             let transaction: Transaction = result.unwrap();
             // Apply transaction to accounts.
-            service.apply_transaction(&transaction);
+            service.apply_transaction(&transaction).unwrap();
         }
-        let want = "client,available,held,total,locked\n1,1.5000,0.0000,1.5000,false\n2,2.0000,0.0000,2.0000,false\n";
+        let want = "client,available,held,total,locked\n1,1.5000,0.0000,1.5000,false\n2,1.0000,0.0000,1.0000,false\n";
         let got = service.print_accounts();
         assert_eq!(got, want);
     }
@@ -186,12 +182,12 @@ mod tests {
     #[test]
     fn dispute_handled_correctly() {
         let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndispute,1,1,\n";
-        let mut rdr = csv::Reader::from_reader(sample_data.as_bytes());
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
         let mut service = TransactionService::new();
         for result in rdr.deserialize() {
             let transaction: Transaction = result.unwrap();
             // Apply transaction to accounts.
-            service.apply_transaction(&transaction);
+            service.apply_transaction(&transaction).unwrap();
         }
         let want = "client,available,held,total,locked\n1,0.0000,5.0000,5.0000,false\n";
         let got = service.print_accounts();
@@ -201,12 +197,12 @@ mod tests {
     #[test]
     fn chargeback_handled_correctly() {
         let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndispute,1,1,0.0\nchargeback,1,1,\n";
-        let mut rdr = csv::Reader::from_reader(sample_data.as_bytes());
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
         let mut service = TransactionService::new();
         for result in rdr.deserialize() {
             let transaction: Transaction = result.unwrap();
             // Apply transaction to accounts.
-            service.apply_transaction(&transaction);
+            service.apply_transaction(&transaction).unwrap();
         }
         let want = "client,available,held,total,locked\n1,0.0000,0.0000,0.0000,true\n";
         let got = service.print_accounts();
@@ -216,15 +212,98 @@ mod tests {
     #[test]
     fn resolve_handled_correctly() {
         let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndispute,1,1,0.0\nresolve,1,1,\n";
-        let mut rdr = csv::Reader::from_reader(sample_data.as_bytes());
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
         let mut service = TransactionService::new();
         for result in rdr.deserialize() {
             let transaction: Transaction = result.unwrap();
             // Apply transaction to accounts.
-            service.apply_transaction(&transaction);
+            service.apply_transaction(&transaction).unwrap();
         }
         let want = "client,available,held,total,locked\n1,5.0000,0.0000,5.0000,false\n";
         let got = service.print_accounts();
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn missing_amount_on_deposit_is_an_error() {
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndeposit,1,2,\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        let mut records = rdr.deserialize();
+        let first: Transaction = records.next().unwrap().unwrap();
+        service.apply_transaction(&first).unwrap();
+        let second: Transaction = records.next().unwrap().unwrap();
+        let err = service.apply_transaction(&second).unwrap_err();
+        assert_eq!(err, LedgerError::MissingAmount);
+    }
+
+    #[test]
+    fn withdrawal_beyond_available_funds_is_an_error() {
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\nwithdrawal,1,2,10.0\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        let mut records = rdr.deserialize();
+        let first: Transaction = records.next().unwrap().unwrap();
+        service.apply_transaction(&first).unwrap();
+        let second: Transaction = records.next().unwrap().unwrap();
+        let err = service.apply_transaction(&second).unwrap_err();
+        assert_eq!(err, LedgerError::NotEnoughFunds);
+    }
+
+    #[test]
+    fn disputing_an_already_disputed_tx_is_an_error() {
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndispute,1,1,\ndispute,1,1,\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        let mut records = rdr.deserialize();
+        for _ in 0..2 {
+            let transaction: Transaction = records.next().unwrap().unwrap();
+            service.apply_transaction(&transaction).unwrap();
+        }
+        let second_dispute: Transaction = records.next().unwrap().unwrap();
+        let err = service.apply_transaction(&second_dispute).unwrap_err();
+        assert_eq!(err, LedgerError::AlreadyDisputed);
+    }
+
+    #[test]
+    fn resolving_a_tx_that_was_never_disputed_is_an_error() {
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\nresolve,1,1,\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        let mut records = rdr.deserialize();
+        let deposit: Transaction = records.next().unwrap().unwrap();
+        service.apply_transaction(&deposit).unwrap();
+        let resolve: Transaction = records.next().unwrap().unwrap();
+        let err = service.apply_transaction(&resolve).unwrap_err();
+        assert_eq!(err, LedgerError::NotDisputed);
+    }
+
+    #[test]
+    fn disputing_another_clients_tx_is_an_error() {
+        let sample_data = "type,client,tx,amount\ndeposit,1,1,5.0\ndeposit,2,2,5.0\ndispute,2,1,\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        let mut records = rdr.deserialize();
+        for _ in 0..2 {
+            let transaction: Transaction = records.next().unwrap().unwrap();
+            service.apply_transaction(&transaction).unwrap();
+        }
+        let dispute: Transaction = records.next().unwrap().unwrap();
+        let err = service.apply_transaction(&dispute).unwrap_err();
+        assert_eq!(err, LedgerError::WrongClient);
+    }
+
+    #[test]
+    fn tolerates_padded_whitespace_and_omitted_trailing_amount() {
+        let sample_data = "type, client, tx, amount\ndeposit, 1, 1, 5.0\ndispute, 1, 1\n";
+        let mut rdr = crate::configured_reader_builder().from_reader(sample_data.as_bytes());
+        let mut service = TransactionService::new();
+        for result in rdr.deserialize() {
+            let transaction: Transaction = result.unwrap();
+            service.apply_transaction(&transaction).unwrap();
+        }
+        let want = "client,available,held,total,locked\n1,0.0000,5.0000,5.0000,false\n";
+        let got = service.print_accounts();
+        assert_eq!(got, want);
+    }
 }
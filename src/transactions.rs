@@ -1,5 +1,8 @@
 use std::collections::HashMap;
-use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer};
+use crate::error::LedgerError;
 
 type TransactionID = u32;
 
@@ -28,12 +31,14 @@ impl TransactionCache {
         self
     }
 
-    pub fn contains_key(&mut self, tx_id: &u32) -> bool {
-        self.store.contains_key(tx_id)
-    }
-
-    pub fn get_mut(&mut self, tx_id: &u32) -> Option<&mut Transaction> {
-        self.store.get_mut(tx_id)
+    // get_owned_mut looks up a transaction by id and verifies that it belongs to client_id,
+    // so a dispute/resolve/chargeback can't be used to reach into another client's transaction.
+    pub fn get_owned_mut(&mut self, tx_id: &u32, client_id: u16) -> Result<&mut Transaction, LedgerError> {
+        let tx = self.store.get_mut(tx_id).ok_or(LedgerError::UnknownTx(client_id, *tx_id))?;
+        if tx.client_id != client_id {
+            return Err(LedgerError::WrongClient);
+        }
+        Ok(tx)
     }
 }
 
@@ -46,9 +51,9 @@ pub struct Transaction {
     #[serde(rename(deserialize = "tx"))]
     pub tx_id: u32,
     #[serde(default)]
-    pub amount: Option<f32>,
+    pub amount: Option<Amount>,
     #[serde(skip_deserializing)]
-    pub disputed: bool,
+    pub state: TxState,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -59,4 +64,112 @@ pub enum TransactionType {
     Dispute,
     Resolve,
     Chargeback,
-}
\ No newline at end of file
+}
+
+// TxState tracks the dispute lifecycle of a deposit or withdrawal. A fresh transaction starts
+// out Processed; the only legal transitions are Processed -> Disputed, Disputed -> Resolved, and
+// Disputed -> ChargedBack. Enforcing these in the handlers (rather than trusting a bare bool)
+// rejects a second dispute on the same tx, a resolve/chargeback on a tx that was never disputed,
+// and a dispute reopened after it was already resolved or charged back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxState {
+    #[default]
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
+// Number of ten-thousandths per whole unit. All Amount values are stored scaled by this factor
+// so that the four published decimal places are exact integers rather than binary floats.
+const SCALE: i64 = 10_000;
+
+// Amount represents a monetary value with exactly four decimal places of precision, stored
+// internally as an i64 count of ten-thousandths. This avoids the rounding drift that comes
+// from accumulating f32 deposits/withdrawals across a long transaction stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount(i64);
+
+impl Amount {
+    pub fn zero() -> Amount {
+        Amount(0)
+    }
+
+    // checked_add returns None if adding the two amounts would overflow an i64.
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    // checked_sub returns None if subtracting the two amounts would overflow an i64.
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+// ParseAmountError is returned when a string cannot be parsed as an Amount, e.g. it has more
+// than four fractional digits or isn't numeric at all.
+#[derive(Debug)]
+pub struct ParseAmountError;
+
+impl fmt::Display for ParseAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid amount: expected a decimal with at most four fractional digits")
+    }
+}
+
+impl std::error::Error for ParseAmountError {}
+
+impl FromStr for Amount {
+    type Err = ParseAmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let negative = s.starts_with('-');
+        let unsigned = s.strip_prefix('-').unwrap_or(s);
+
+        let mut parts = unsigned.splitn(2, '.');
+        let whole_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+
+        if frac_part.len() > 4 {
+            return Err(ParseAmountError);
+        }
+
+        let whole: i64 = whole_part.parse().map_err(|_| ParseAmountError)?;
+        let mut frac_digits = frac_part.to_string();
+        while frac_digits.len() < 4 {
+            frac_digits.push('0');
+        }
+        let frac: i64 = frac_digits.parse().map_err(|_| ParseAmountError)?;
+
+        let magnitude = whole
+            .checked_mul(SCALE)
+            .and_then(|scaled| scaled.checked_add(frac))
+            .ok_or(ParseAmountError)?;
+
+        Ok(Amount(if negative { -magnitude } else { magnitude }))
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let negative = self.0 < 0;
+        let magnitude = self.0.abs();
+        let whole = magnitude / SCALE;
+        let frac = magnitude % SCALE;
+        if negative {
+            write!(f, "-{}.{:04}", whole, frac)
+        } else {
+            write!(f, "{}.{:04}", whole, frac)
+        }
+    }
+}